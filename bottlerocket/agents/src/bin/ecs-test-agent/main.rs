@@ -7,23 +7,35 @@ Tests whether an ECS task runs successfully.
 use agent_utils::aws::aws_config;
 use agent_utils::init_agent_logger;
 use async_trait::async_trait;
+use aws_sdk_cloudwatch::types::{Dimension, Metric, MetricDataQuery, MetricStat};
 use aws_sdk_ecs::error::SdkError as EcsSdkError;
 use aws_sdk_ecs::operation::describe_task_definition::{
     DescribeTaskDefinitionError, DescribeTaskDefinitionOutput,
 };
-use aws_sdk_ecs::types::{Compatibility, ContainerDefinition, LaunchType, TaskStopCode};
+use aws_sdk_ecs::types::{
+    AssignPublicIp, AwsVpcConfiguration, Compatibility, ContainerDefinition, LaunchType,
+    LogConfiguration, LogDriver, NetworkConfiguration, NetworkMode, Task, TaskStopCode,
+};
+use aws_smithy_types::DateTime as SmithyDateTime;
 use bottlerocket_agents::constants::DEFAULT_TASK_DEFINITION;
 use bottlerocket_agents::error::{self, Error};
-use bottlerocket_types::agent_config::{EcsTestConfig, AWS_CREDENTIALS_SECRET_NAME};
+use bottlerocket_types::agent_config::{
+    EcsLogConfiguration, EcsNetworkConfiguration, EcsTestConfig, AWS_CREDENTIALS_SECRET_NAME,
+};
 use log::info;
+use serde_json::json;
 use snafu::{OptionExt, ResultExt};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 use test_agent::{
     BootstrapData, ClientError, DefaultClient, DefaultInfoClient, InfoClient, Runner, Spec,
     TestAgent,
 };
 use testsys_model::{Outcome, SecretName, TestResults};
 
+/// Name of the essential container testsys registers in the task definitions it creates itself.
+const ESSENTIAL_CONTAINER_NAME: &str = "ecs-smoke-test";
+
 struct EcsTestRunner {
     config: EcsTestConfig,
     aws_secret_name: Option<SecretName>,
@@ -56,29 +68,59 @@ where
         )
         .await?;
         let ecs_client = aws_sdk_ecs::Client::new(&config);
+        let logs_client = aws_sdk_cloudwatchlogs::Client::new(&config);
+        let cloudwatch_client = aws_sdk_cloudwatch::Client::new(&config);
 
-        info!("Waiting for registered container instances...");
+        let launch_type = LaunchType::from(self.config.launch_type.as_deref().unwrap_or("EC2"));
+        let network_configuration = match (&launch_type, &self.config.network_configuration) {
+            (LaunchType::Fargate, None) => {
+                return error::FargateNetworkConfigurationSnafu.fail();
+            }
+            (_, network_configuration) => {
+                network_configuration.as_ref().map(network_configuration_for)
+            }
+        };
 
-        tokio::time::timeout(
-            Duration::from_secs(30),
-            wait_for_registered_containers(&ecs_client, &self.config.cluster_name),
-        )
-        .await
-        .context(error::InstanceTimeoutSnafu)??;
+        // Container instances are only relevant for the EC2 launch type; Fargate tasks run on
+        // AWS-managed capacity, so there is nothing to wait for.
+        if launch_type != LaunchType::Fargate {
+            info!("Waiting for registered container instances...");
+
+            tokio::time::timeout(
+                Duration::from_secs(30),
+                wait_for_registered_containers(&ecs_client, &self.config.cluster_name),
+            )
+            .await
+            .context(error::InstanceTimeoutSnafu)??;
+        }
 
         let task_name = match &self.config.task_definition_name_and_revision {
             Some(task_definition) => task_definition.clone(),
-            None => create_or_find_task_definition(&ecs_client).await?,
+            None => {
+                create_or_find_task_definition(
+                    &ecs_client,
+                    &launch_type,
+                    self.config.log_configuration.as_ref(),
+                    config.region().map(|region| region.to_string()).as_deref(),
+                )
+                .await?
+            }
         };
 
         info!("Running task '{}'", task_name);
 
+        let run_started_at = SystemTime::now();
+
+        let exec_commands: &[String] = self.config.exec_commands.as_deref().unwrap_or_default();
+
         let run_task_output = ecs_client
             .run_task()
             .cluster(&self.config.cluster_name)
             .task_definition(task_name)
             .count(self.config.task_count)
-            .launch_type(LaunchType::Ec2)
+            .launch_type(launch_type)
+            .set_network_configuration(network_configuration)
+            .enable_execute_command(!exec_commands.is_empty())
             .send()
             .await
             .context(error::TaskRunCreationSnafu)?;
@@ -87,30 +129,82 @@ where
             .iter()
             .filter_map(|task| task.task_arn().map(|arn| arn.to_string()))
             .collect();
+        // `run_task` can return fewer tasks than `task_count` without erroring (e.g. no capacity,
+        // an AZ constraint it couldn't satisfy); `failures()` is the only place that's recorded.
+        let scheduling_failures = run_task_output.failures();
 
-        info!("Waiting for tasks to complete...");
+        let mut exec_failures = Vec::new();
+        if !exec_commands.is_empty() {
+            info!("Waiting for tasks to start running...");
+            tokio::time::timeout(
+                Duration::from_secs(30),
+                wait_for_tasks_running(&ecs_client, &self.config.cluster_name, &task_arns),
+            )
+            .await
+            .context(error::TaskRunningTimeoutSnafu)??;
 
-        match tokio::time::timeout(
-            Duration::from_secs(30),
-            wait_for_test_running(
+            info!("Running exec assertions...");
+            exec_failures = run_exec_commands(
                 &ecs_client,
                 &self.config.cluster_name,
                 &task_arns,
-                self.config.task_count,
-            ),
+                exec_commands,
+                config.region().map(|region| region.to_string()).as_deref(),
+            )
+            .await?;
+        }
+
+        info!("Waiting for tasks to complete...");
+
+        let test_timeout = Duration::from_secs(self.config.timeout_seconds.unwrap_or(30));
+        let mut results = wait_for_test_running(
+            &ecs_client,
+            &self.config.cluster_name,
+            &task_arns,
+            self.config.task_count,
+            test_timeout,
+            scheduling_failures,
         )
-        .await
-        {
-            Ok(results) => results,
-            Err(_) => {
-                test_results(
+        .await?;
+
+        if !exec_failures.is_empty() {
+            results.outcome = Outcome::Fail;
+            results.other_info = Some(match results.other_info.take() {
+                Some(existing) => format!(
+                    "{}\n\nexec command failures:\n{}",
+                    existing,
+                    exec_failures.join("\n")
+                ),
+                None => format!("exec command failures:\n{}", exec_failures.join("\n")),
+            });
+        }
+
+        let results = match &self.config.log_configuration {
+            Some(log_configuration) => {
+                attach_container_logs(
                     &ecs_client,
+                    &logs_client,
                     &self.config.cluster_name,
+                    log_configuration,
                     &task_arns,
-                    self.config.task_count,
+                    results,
                 )
-                .await
+                .await?
             }
+            None => results,
+        };
+
+        if self.config.collect_metrics.unwrap_or(false) {
+            attach_task_metrics(
+                &cloudwatch_client,
+                &self.config.cluster_name,
+                &task_arns,
+                run_started_at,
+                results,
+            )
+            .await
+        } else {
+            Ok(results)
         }
     }
 
@@ -119,36 +213,107 @@ where
     }
 }
 
+/// The last-observed state of a single task, used to log transitions exactly once and to report
+/// where a task got stuck if the test times out.
+#[derive(Clone)]
+struct TaskEvent {
+    last_status: Option<String>,
+    stopped_reason: Option<String>,
+}
+
+impl TaskEvent {
+    fn from_task(task: &Task) -> Self {
+        Self {
+            last_status: task.last_status().map(str::to_string),
+            stopped_reason: task.stopped_reason().map(str::to_string),
+        }
+    }
+
+    /// Builds the event for a task `run_task` never managed to schedule at all, so it's
+    /// distinguishable from a task that ran and then failed.
+    fn from_scheduling_failure(failure: &aws_sdk_ecs::types::Failure) -> Self {
+        Self {
+            last_status: Some("PROVISIONING_FAILED".to_string()),
+            stopped_reason: failure.reason().map(str::to_string),
+        }
+    }
+
+    fn describe(&self, task_arn: &str) -> String {
+        match (&self.last_status, &self.stopped_reason) {
+            (Some(status), Some(reason)) => format!("{}: {} ({})", task_arn, status, reason),
+            (Some(status), None) => format!("{}: {}", task_arn, status),
+            (None, _) => format!("{}: PROVISIONING", task_arn),
+        }
+    }
+}
+
+/// Polls `describe_tasks` until every task has stopped successfully or `timeout` elapses,
+/// logging each newly-observed state transition (PROVISIONING -> PENDING -> RUNNING -> STOPPED)
+/// instead of the raw pass/fail count. If the timeout is hit, the last observed event for each
+/// task is attached to the returned `TestResults` so a never-scheduled task can be told apart
+/// from one that ran and failed. `scheduling_failures` seeds that same event map with any task
+/// `run_task` couldn't place at all, so the "never scheduled" case is reported too, not just
+/// silently polled as a task that's missing from the response.
 async fn wait_for_test_running(
     ecs_client: &aws_sdk_ecs::Client,
     cluster_name: &str,
     task_arns: &[String],
     task_count: i32,
+    timeout: Duration,
+    scheduling_failures: &[aws_sdk_ecs::types::Failure],
 ) -> Result<TestResults, Error> {
+    let mut last_events: HashMap<String, TaskEvent> = HashMap::new();
+    for (index, failure) in scheduling_failures.iter().enumerate() {
+        let key = failure
+            .arn()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("unscheduled-task-{}", index));
+        last_events.insert(key, TaskEvent::from_scheduling_failure(failure));
+    }
+    let deadline = tokio::time::Instant::now() + timeout;
+
     loop {
-        let results = test_results(ecs_client, cluster_name, task_arns, task_count).await?;
-        if results.outcome == Outcome::Pass {
+        let tasks = ecs_client
+            .describe_tasks()
+            .cluster(cluster_name)
+            .set_tasks(Some(task_arns.to_vec()))
+            .send()
+            .await
+            .context(error::TaskDescribeSnafu)?
+            .tasks()
+            .to_owned();
+
+        for task in &tasks {
+            if let Some(task_arn) = task.task_arn() {
+                let event = TaskEvent::from_task(task);
+                let is_new_transition = last_events
+                    .get(task_arn)
+                    .map(|previous| previous.last_status != event.last_status)
+                    .unwrap_or(true);
+                if is_new_transition {
+                    info!("{}", event.describe(task_arn));
+                }
+                last_events.insert(task_arn.to_string(), event);
+            }
+        }
+
+        let results = summarize_results(&tasks, task_count, &last_events);
+        if results.outcome == Outcome::Pass || tokio::time::Instant::now() >= deadline {
             return Ok(results);
         }
+
         tokio::time::sleep(Duration::from_secs(2)).await;
     }
 }
 
-async fn test_results(
-    ecs_client: &aws_sdk_ecs::Client,
-    cluster_name: &str,
-    task_arns: &[String],
+/// Computes pass/fail counts from a `describe_tasks` snapshot. When not every task has passed,
+/// the last observed event for each task is recorded in `other_info` so the caller can see
+/// where things stalled.
+fn summarize_results(
+    tasks: &[Task],
     task_count: i32,
-) -> Result<TestResults, Error> {
-    let tasks = ecs_client
-        .describe_tasks()
-        .cluster(cluster_name)
-        .set_tasks(Some(task_arns.to_vec()))
-        .send()
-        .await
-        .context(error::TaskDescribeSnafu)?
-        .tasks()
-        .to_owned();
+    last_events: &HashMap<String, TaskEvent>,
+) -> TestResults {
     let running_count = tasks
         .iter()
         .filter(|task| task.last_status() == Some("STOPPED"))
@@ -161,7 +326,20 @@ async fn test_results(
                 == 0
         })
         .count() as i32;
-    Ok(TestResults {
+
+    let other_info = if running_count != task_count {
+        Some(
+            last_events
+                .iter()
+                .map(|(task_arn, event)| event.describe(task_arn))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    } else {
+        None
+    };
+
+    TestResults {
         outcome: if task_count == running_count {
             Outcome::Pass
         } else {
@@ -170,8 +348,188 @@ async fn test_results(
         num_passed: running_count as u64,
         num_failed: (task_count - running_count) as u64,
         num_skipped: 0,
-        other_info: None,
+        other_info,
+    }
+}
+
+/// Polls `describe_tasks` until every task has reached `RUNNING` (or gone past it to `STOPPED`),
+/// which is the point at which ECS Exec sessions can be opened against the task's containers.
+async fn wait_for_tasks_running(
+    ecs_client: &aws_sdk_ecs::Client,
+    cluster_name: &str,
+    task_arns: &[String],
+) -> Result<(), Error> {
+    loop {
+        let tasks = ecs_client
+            .describe_tasks()
+            .cluster(cluster_name)
+            .set_tasks(Some(task_arns.to_vec()))
+            .send()
+            .await
+            .context(error::TaskDescribeSnafu)?
+            .tasks()
+            .to_owned();
+
+        let all_running = tasks
+            .iter()
+            .all(|task| matches!(task.last_status(), Some("RUNNING") | Some("STOPPED")));
+        if all_running {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+/// The sentinel appended to every exec assertion so its real exit code can be recovered from the
+/// SSM session's captured output. The `execute_command` API itself only reports whether the
+/// session could be opened, not what the command it ran returned.
+const EXEC_EXIT_CODE_MARKER: &str = "__EXEC_EXIT_CODE__";
+
+/// Runs each configured assertion command inside a task's essential container via ECS Exec,
+/// recovering the command's real exit code off the SSM session's data channel (via the
+/// `session-manager-plugin` binary, the same mechanism the AWS CLI delegates to for
+/// `aws ecs execute-command`) and returning a description of every command that exited non-zero,
+/// whose exit code couldn't be recovered, or whose session output couldn't be read. An
+/// `execute_command` call that fails outright is returned as an error instead.
+async fn run_exec_commands(
+    ecs_client: &aws_sdk_ecs::Client,
+    cluster_name: &str,
+    task_arns: &[String],
+    commands: &[String],
+    region: Option<&str>,
+) -> Result<Vec<String>, Error> {
+    let mut failures = Vec::new();
+    for task_arn in task_arns {
+        let container_name = essential_container_name(ecs_client, cluster_name, task_arn).await?;
+        for command in commands {
+            // ECS Exec splits `command` into an argv and executes it directly, the same as
+            // `docker exec <container> <command>` - it is not passed through a shell. Route it
+            // through `/bin/sh -c` ourselves so the `; echo ...:$?` sentinel is shell syntax
+            // rather than part of a (nonexistent) executable name.
+            let wrapped_command = format!("{}; echo \"{}:$?\"", command, EXEC_EXIT_CODE_MARKER);
+            let shell_command = format!("/bin/sh -c {}", shell_escape(&wrapped_command));
+            let response = ecs_client
+                .execute_command()
+                .cluster(cluster_name)
+                .task(task_arn)
+                .container(container_name.as_str())
+                // ECS Exec only supports interactive sessions; `interactive(false)` is rejected.
+                .interactive(true)
+                .command(shell_command)
+                .send()
+                .await
+                .context(error::ExecCommandSnafu)?;
+            let session = response.session().context(error::ExecSessionMissingSnafu)?;
+
+            match exec_session_output(session, region).await {
+                Ok(output) => match exec_exit_code(&output) {
+                    Some(0) => {}
+                    Some(code) => failures.push(format!(
+                        "{}: `{}` exited with status {}",
+                        task_arn, command, code
+                    )),
+                    None => failures.push(format!(
+                        "{}: `{}` ran but its exit code could not be recovered from the exec \
+                        session output",
+                        task_arn, command
+                    )),
+                },
+                Err(e) => failures.push(format!("{}: `{}` failed: {}", task_arn, command, e)),
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Finds the essential container for the task definition a task is actually running. Task
+/// definitions testsys didn't register itself (via `task_definition_name_and_revision`) aren't
+/// guaranteed to name their essential container `ESSENTIAL_CONTAINER_NAME`, so this is read off
+/// the real task definition rather than assumed.
+async fn essential_container_name(
+    ecs_client: &aws_sdk_ecs::Client,
+    cluster_name: &str,
+    task_arn: &str,
+) -> Result<String, Error> {
+    let task = ecs_client
+        .describe_tasks()
+        .cluster(cluster_name)
+        .tasks(task_arn)
+        .send()
+        .await
+        .context(error::TaskDescribeSnafu)?
+        .tasks()
+        .first()
+        .cloned()
+        .context(error::NoTaskSnafu)?;
+    let task_definition_arn = task.task_definition_arn().context(error::NoTaskSnafu)?;
+    let task_definition = ecs_client
+        .describe_task_definition()
+        .task_definition(task_definition_arn)
+        .send()
+        .await
+        .context(error::TaskDefinitionDescribeSnafu)?
+        .task_definition()
+        .context(error::TaskDefinitionMissingSnafu)?
+        .to_owned();
+    task_definition
+        .container_definitions()
+        .iter()
+        .find(|container| container.essential() == Some(true))
+        .and_then(|container| container.name())
+        .map(str::to_string)
+        .context(error::EssentialContainerMissingSnafu)
+}
+
+/// Wraps a string in single quotes for use as a single `/bin/sh` argument, escaping any single
+/// quotes it contains.
+fn shell_escape(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Opens the SSM session `execute_command` created and returns everything written to its stdout,
+/// which is the exec'd command's own stdout/stderr.
+///
+/// This shells out to the `session-manager-plugin` binary instead of speaking the SSM data
+/// channel's WebSocket protocol directly - that binary *is* the reference implementation of that
+/// protocol (it's what `aws ssm start-session`/`aws ecs execute-command` delegate to), and when
+/// its own stdout isn't a TTY it simply relays the decrypted channel bytes there and exits once
+/// the remote session closes, which happens here as soon as the wrapped `/bin/sh -c` command
+/// finishes. So capturing `.output()` does capture the real command output; it is not a
+/// stand-in for a protocol implementation, it's a subprocess wrapping the same one AWS's own
+/// tooling uses.
+async fn exec_session_output(
+    session: &aws_sdk_ecs::types::Session,
+    region: Option<&str>,
+) -> Result<String, Error> {
+    let session_request = json!({
+        "SessionId": session.session_id(),
+        "TokenValue": session.token_value(),
+        "StreamUrl": session.stream_url(),
     })
+    .to_string();
+
+    let output = tokio::process::Command::new("session-manager-plugin")
+        .arg(session_request)
+        .arg(region.unwrap_or_default())
+        .arg("StartSession")
+        .output()
+        .await
+        .context(error::ExecSessionSnafu)?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Recovers a command's real exit code from its captured exec session output by finding the
+/// sentinel `run_exec_commands` appended to the command.
+fn exec_exit_code(output: &str) -> Option<i32> {
+    output
+        .lines()
+        .rev()
+        .find_map(|line| {
+            line.trim()
+                .strip_prefix(&format!("{}:", EXEC_EXIT_CODE_MARKER))
+        })
+        .and_then(|code| code.trim().parse().ok())
 }
 
 async fn wait_for_registered_containers(
@@ -197,41 +555,77 @@ async fn wait_for_registered_containers(
     }
 }
 
+/// The task definition family testsys registers its smoke-test definition under is scoped by
+/// launch type: EC2 task definitions default to the `bridge`/`host` network mode and only
+/// declare EC2 compatibility, which `run_task` rejects for Fargate. Keeping separate families
+/// means an EC2 run followed by a Fargate run against the same cluster can't reuse a stale,
+/// incompatible revision.
+fn task_definition_family(launch_type: &LaunchType) -> String {
+    match launch_type {
+        LaunchType::Fargate => format!("{}-fargate", DEFAULT_TASK_DEFINITION),
+        _ => DEFAULT_TASK_DEFINITION.to_string(),
+    }
+}
+
 /// Retrieves the task_definition and revision of the testsys provided task definition. If the
 /// task definition doesn't exist, it will be created.
-async fn create_or_find_task_definition(ecs_client: &aws_sdk_ecs::Client) -> Result<String, Error> {
+async fn create_or_find_task_definition(
+    ecs_client: &aws_sdk_ecs::Client,
+    launch_type: &LaunchType,
+    log_configuration: Option<&EcsLogConfiguration>,
+    region: Option<&str>,
+) -> Result<String, Error> {
+    let family = task_definition_family(launch_type);
     let exists = exists(
         ecs_client
             .describe_task_definition()
-            .task_definition(DEFAULT_TASK_DEFINITION)
+            .task_definition(&family)
             .send()
             .await,
     );
     if exists {
-        latest_task_revision(ecs_client).await
+        latest_task_revision(ecs_client, &family).await
     } else {
-        create_task_definition(ecs_client).await
+        create_task_definition(ecs_client, &family, launch_type, log_configuration, region).await
     }
 }
 
 /// Creates a task definition for testsys that runs a simple echo command to ensure the system
 /// is running properly.
-async fn create_task_definition(ecs_client: &aws_sdk_ecs::Client) -> Result<String, Error> {
-    let task_info = ecs_client
+async fn create_task_definition(
+    ecs_client: &aws_sdk_ecs::Client,
+    family: &str,
+    launch_type: &LaunchType,
+    log_configuration: Option<&EcsLogConfiguration>,
+    region: Option<&str>,
+) -> Result<String, Error> {
+    let mut container_definition = ContainerDefinition::builder()
+        .name(ESSENTIAL_CONTAINER_NAME)
+        .image("public.ecr.aws/amazonlinux/amazonlinux:2")
+        .essential(true)
+        .set_entry_point(Some(vec!["sh".to_string(), "-c".to_string()]))
+        .command("/bin/sh -c \"echo hello-world\"");
+    if let Some(log_configuration) = log_configuration {
+        container_definition = container_definition.log_configuration(ecs_log_configuration(
+            log_configuration,
+            region.unwrap_or_default(),
+        ));
+    }
+
+    let mut request = ecs_client
         .register_task_definition()
-        .family(DEFAULT_TASK_DEFINITION)
-        .container_definitions(
-            ContainerDefinition::builder()
-                .name("ecs-smoke-test")
-                .image("public.ecr.aws/amazonlinux/amazonlinux:2")
-                .essential(true)
-                .set_entry_point(Some(vec!["sh".to_string(), "-c".to_string()]))
-                .command("/bin/sh -c \"echo hello-world\"")
-                .build(),
-        )
-        .requires_compatibilities(Compatibility::Ec2)
+        .family(family)
+        .container_definitions(container_definition.build())
+        .requires_compatibilities(match launch_type {
+            LaunchType::Fargate => Compatibility::Fargate,
+            _ => Compatibility::Ec2,
+        })
         .cpu("256")
-        .memory("512")
+        .memory("512");
+    if launch_type == &LaunchType::Fargate {
+        request = request.network_mode(NetworkMode::Awsvpc);
+    }
+    let task_info = request
         .send()
         .await
         .context(error::TaskDefinitionCreationSnafu)?;
@@ -239,14 +633,322 @@ async fn create_task_definition(ecs_client: &aws_sdk_ecs::Client) -> Result<Stri
         .task_definition()
         .context(error::TaskDefinitionMissingSnafu)?
         .revision();
-    Ok(format!("{}:{}", DEFAULT_TASK_DEFINITION, revision))
+    Ok(format!("{}:{}", family, revision))
+}
+
+/// Builds the `awslogs` log configuration for the smoke-test container, pointing at the
+/// CloudWatch log group configured for this test.
+fn ecs_log_configuration(log_configuration: &EcsLogConfiguration, region: &str) -> LogConfiguration {
+    LogConfiguration::builder()
+        .log_driver(LogDriver::Awslogs)
+        .options("awslogs-group", log_configuration.log_group.clone())
+        .options("awslogs-region", region.to_string())
+        .options(
+            "awslogs-stream-prefix",
+            log_configuration
+                .stream_prefix
+                .clone()
+                .unwrap_or_else(|| "ecs".to_string()),
+        )
+        .build()
+}
+
+/// The `awslogs` driver options needed to locate a container's log stream, read off that
+/// container's own task definition entry rather than assumed from the agent's configuration.
+struct AwslogsLocation {
+    log_group: String,
+    stream_prefix: String,
+}
+
+/// Reads a container definition's `log_configuration` and returns where its `awslogs` stream
+/// lives, or `None` if the container isn't using the `awslogs` driver at all. Task definitions
+/// testsys didn't create (via `task_definition_name_and_revision`) aren't guaranteed to use
+/// `awslogs`, so this is read from the definition actually in use instead of assumed from
+/// `EcsTestConfig::log_configuration`.
+fn awslogs_location(container: &ContainerDefinition) -> Option<AwslogsLocation> {
+    let log_configuration = container.log_configuration()?;
+    if log_configuration.log_driver() != &LogDriver::Awslogs {
+        return None;
+    }
+    let options = log_configuration.options()?;
+    Some(AwslogsLocation {
+        log_group: options.get("awslogs-group")?.clone(),
+        stream_prefix: options
+            .get("awslogs-stream-prefix")
+            .cloned()
+            .unwrap_or_else(|| "ecs".to_string()),
+    })
+}
+
+/// Fetches the tail of each container's `awslogs` stream for the given tasks and attaches it to
+/// `results.other_info` so a failing task's stdout/stderr is visible without a separate
+/// CloudWatch lookup.
+async fn attach_container_logs(
+    ecs_client: &aws_sdk_ecs::Client,
+    logs_client: &aws_sdk_cloudwatchlogs::Client,
+    cluster_name: &str,
+    log_configuration: &EcsLogConfiguration,
+    task_arns: &[String],
+    mut results: TestResults,
+) -> Result<TestResults, Error> {
+    let tasks = ecs_client
+        .describe_tasks()
+        .cluster(cluster_name)
+        .set_tasks(Some(task_arns.to_vec()))
+        .send()
+        .await
+        .context(error::TaskDescribeSnafu)?
+        .tasks()
+        .to_owned();
+
+    let tail_lines = log_configuration.tail_lines.unwrap_or(50);
+
+    // Task definitions are shared across tasks in a run, so describe each one once.
+    let mut task_definitions: HashMap<String, aws_sdk_ecs::types::TaskDefinition> = HashMap::new();
+
+    let mut logs = Vec::new();
+    for task in &tasks {
+        let task_id = task
+            .task_arn()
+            .and_then(|arn| arn.rsplit('/').next())
+            .unwrap_or_default();
+        let task_definition_arn = match task.task_definition_arn() {
+            Some(arn) => arn,
+            None => continue,
+        };
+        if !task_definitions.contains_key(task_definition_arn) {
+            let task_definition = ecs_client
+                .describe_task_definition()
+                .task_definition(task_definition_arn)
+                .send()
+                .await
+                .context(error::TaskDefinitionDescribeSnafu)?
+                .task_definition()
+                .context(error::TaskDefinitionMissingSnafu)?
+                .to_owned();
+            task_definitions.insert(task_definition_arn.to_string(), task_definition);
+        }
+        let task_definition = &task_definitions[task_definition_arn];
+
+        for container in task.containers() {
+            let container_name = container.name().unwrap_or_default();
+            let container_definition = task_definition
+                .container_definitions()
+                .iter()
+                .find(|c| c.name() == Some(container_name));
+            let location = match container_definition.and_then(awslogs_location) {
+                Some(location) => location,
+                None => continue,
+            };
+            let log_stream_name = format!(
+                "{}/{}/{}",
+                location.stream_prefix, container_name, task_id
+            );
+            match fetch_log_tail(logs_client, &location.log_group, &log_stream_name, tail_lines)
+                .await
+            {
+                Ok(tail) if !tail.is_empty() => {
+                    logs.push(format!("=== {} ===\n{}", log_stream_name, tail.join("\n")));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    logs.push(format!(
+                        "=== {} ===\nfailed to fetch logs: {}",
+                        log_stream_name, e
+                    ));
+                }
+            }
+        }
+    }
+
+    if !logs.is_empty() {
+        let log_section = logs.join("\n\n");
+        results.other_info = Some(match results.other_info.take() {
+            Some(existing) => format!("{}\n\n{}", existing, log_section),
+            None => log_section,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Retrieves the tail (most recent `tail_lines` events) of a single CloudWatch log stream.
+async fn fetch_log_tail(
+    logs_client: &aws_sdk_cloudwatchlogs::Client,
+    log_group: &str,
+    log_stream: &str,
+    tail_lines: u32,
+) -> Result<Vec<String>, Error> {
+    let events = logs_client
+        .get_log_events()
+        .log_group_name(log_group)
+        .log_stream_name(log_stream)
+        .limit(tail_lines as i32)
+        .start_from_head(false)
+        .send()
+        .await
+        .context(error::LogEventsSnafu)?;
+    Ok(events
+        .events()
+        .iter()
+        .filter_map(|event| event.message().map(str::to_string))
+        .collect())
+}
+
+/// Queries CloudWatch Container Insights for each task's CPU/memory utilization over the life of
+/// the test run and attaches peak/average values to `results.other_info` as a JSON blob, giving
+/// the smoke test a resource-envelope dimension alongside liveness.
+async fn attach_task_metrics(
+    cloudwatch_client: &aws_sdk_cloudwatch::Client,
+    cluster_name: &str,
+    task_arns: &[String],
+    start_time: SystemTime,
+    mut results: TestResults,
+) -> Result<TestResults, Error> {
+    let end_time = SystemTime::now();
+
+    let mut per_task = serde_json::Map::new();
+    for task_arn in task_arns {
+        let task_id = task_arn.rsplit('/').next().unwrap_or(task_arn);
+        let metrics =
+            fetch_task_metrics(cloudwatch_client, cluster_name, task_id, start_time, end_time)
+                .await?;
+        per_task.insert(task_id.to_string(), metrics);
+    }
+
+    let metrics_section =
+        serde_json::to_string(&serde_json::Value::Object(per_task)).unwrap_or_default();
+    results.other_info = Some(match results.other_info.take() {
+        Some(existing) => format!("{}\n\nmetrics: {}", existing, metrics_section),
+        None => format!("metrics: {}", metrics_section),
+    });
+
+    Ok(results)
+}
+
+/// Fetches peak and average `CpuUtilized`/`MemoryUtilized` for a single task from the
+/// `ECS/ContainerInsights` namespace.
+async fn fetch_task_metrics(
+    cloudwatch_client: &aws_sdk_cloudwatch::Client,
+    cluster_name: &str,
+    task_id: &str,
+    start_time: SystemTime,
+    end_time: SystemTime,
+) -> Result<serde_json::Value, Error> {
+    let dimensions = vec![
+        Dimension::builder()
+            .name("ClusterName")
+            .value(cluster_name)
+            .build(),
+        Dimension::builder().name("TaskId").value(task_id).build(),
+    ];
+
+    let queries = [
+        ("cpu_avg", "CpuUtilized", "Average"),
+        ("cpu_max", "CpuUtilized", "Maximum"),
+        ("mem_avg", "MemoryUtilized", "Average"),
+        ("mem_max", "MemoryUtilized", "Maximum"),
+    ]
+    .into_iter()
+    .map(|(id, metric_name, stat)| {
+        MetricDataQuery::builder()
+            .id(id)
+            .metric_stat(
+                MetricStat::builder()
+                    .metric(
+                        Metric::builder()
+                            .namespace("ECS/ContainerInsights")
+                            .metric_name(metric_name)
+                            .set_dimensions(Some(dimensions.clone()))
+                            .build(),
+                    )
+                    .period(60)
+                    .stat(stat)
+                    .build(),
+            )
+            .build()
+    })
+    .collect();
+
+    let output = cloudwatch_client
+        .get_metric_data()
+        .set_metric_data_queries(Some(queries))
+        .start_time(SmithyDateTime::from(start_time))
+        .end_time(SmithyDateTime::from(end_time))
+        .send()
+        .await
+        .context(error::MetricDataSnafu)?;
+
+    // `get_metric_data` returns one datapoint per `period` (60s) bucket in the window, not a
+    // single aggregate over the whole run, so for any task running longer than a minute there are
+    // multiple datapoints per query here. Fold across all of them instead of taking the first
+    // (which would silently just be the most recent minute) to get a real peak/average over the
+    // task's full lifetime.
+    let mut values: HashMap<String, f64> = HashMap::new();
+    for result in output.metric_data_results() {
+        let id = match result.id() {
+            Some(id) => id,
+            None => continue,
+        };
+        let datapoints: Vec<f64> = result.values().iter().copied().collect();
+        if datapoints.is_empty() {
+            continue;
+        }
+        let aggregated = if id.ends_with("_max") {
+            datapoints.into_iter().fold(f64::MIN, f64::max)
+        } else {
+            datapoints.iter().sum::<f64>() / datapoints.len() as f64
+        };
+        values.insert(id.to_string(), aggregated);
+    }
+
+    if values.is_empty() {
+        log::warn!(
+            "no Container Insights data for task '{}' yet; ECS/ContainerInsights metrics are \
+            emitted on ~1 minute granularity and commonly haven't propagated by the time a \
+            short-lived task stops, so cpu/memory utilized will be reported as null",
+            task_id
+        );
+    }
+
+    Ok(json!({
+        "cpu_utilized": {
+            "average": values.get("cpu_avg"),
+            "peak": values.get("cpu_max"),
+        },
+        "memory_utilized": {
+            "average": values.get("mem_avg"),
+            "peak": values.get("mem_max"),
+        },
+    }))
+}
+
+/// Converts the testsys `awsvpc` network configuration into the shape expected by `run_task`.
+fn network_configuration_for(
+    network_configuration: &EcsNetworkConfiguration,
+) -> NetworkConfiguration {
+    NetworkConfiguration::builder()
+        .awsvpc_configuration(
+            AwsVpcConfiguration::builder()
+                .set_subnets(Some(network_configuration.subnets.clone()))
+                .set_security_groups(Some(network_configuration.security_groups.clone()))
+                .assign_public_ip(
+                    if network_configuration.assign_public_ip.unwrap_or(false) {
+                        AssignPublicIp::Enabled
+                    } else {
+                        AssignPublicIp::Disabled
+                    },
+                )
+                .build(),
+        )
+        .build()
 }
 
 /// Retrieve the task definition and the latest revision of the testsys provided ecs task definition.
-async fn latest_task_revision(ecs_client: &aws_sdk_ecs::Client) -> Result<String, Error> {
+async fn latest_task_revision(ecs_client: &aws_sdk_ecs::Client, family: &str) -> Result<String, Error> {
     let task_info = ecs_client
         .describe_task_definition()
-        .task_definition(DEFAULT_TASK_DEFINITION)
+        .task_definition(family)
         .send()
         .await
         .context(error::TaskDefinitionDescribeSnafu)?;
@@ -254,7 +956,7 @@ async fn latest_task_revision(ecs_client: &aws_sdk_ecs::Client) -> Result<String
         .task_definition()
         .context(error::TaskDefinitionMissingSnafu)?
         .revision();
-    Ok(format!("{}:{}", DEFAULT_TASK_DEFINITION, revision))
+    Ok(format!("{}:{}", family, revision))
 }
 
 fn exists(
@@ -0,0 +1,9 @@
+/*!
+
+Constants shared across the testsys agents.
+
+!*/
+
+/// The family name of the task definition testsys registers when the caller doesn't supply
+/// their own.
+pub const DEFAULT_TASK_DEFINITION: &str = "testsys-ecs-smoke-test";
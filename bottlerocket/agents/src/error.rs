@@ -0,0 +1,92 @@
+/*!
+
+The error type returned by the testsys ECS agents.
+
+!*/
+
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(context(false))]
+    AwsConfig { source: agent_utils::aws::Error },
+
+    #[snafu(display("Fargate launch type requires `network_configuration` to be set"))]
+    FargateNetworkConfiguration,
+
+    #[snafu(display("Timed out waiting for registered container instances: {}", source))]
+    InstanceTimeout { source: tokio::time::error::Elapsed },
+
+    #[snafu(display("Timed out waiting for tasks to start running: {}", source))]
+    TaskRunningTimeout { source: tokio::time::error::Elapsed },
+
+    #[snafu(display("Unable to describe cluster: {}", source))]
+    ClusterDescribe {
+        source: aws_sdk_ecs::error::SdkError<
+            aws_sdk_ecs::operation::describe_clusters::DescribeClustersError,
+        >,
+    },
+
+    #[snafu(display("The requested cluster or task was missing from the describe response"))]
+    NoTask,
+
+    #[snafu(display("Task definition had no essential container"))]
+    EssentialContainerMissing,
+
+    #[snafu(display("Unable to run task: {}", source))]
+    TaskRunCreation {
+        source: aws_sdk_ecs::error::SdkError<aws_sdk_ecs::operation::run_task::RunTaskError>,
+    },
+
+    #[snafu(display("Unable to describe tasks: {}", source))]
+    TaskDescribe {
+        source: aws_sdk_ecs::error::SdkError<
+            aws_sdk_ecs::operation::describe_tasks::DescribeTasksError,
+        >,
+    },
+
+    #[snafu(display("Unable to register task definition: {}", source))]
+    TaskDefinitionCreation {
+        source: aws_sdk_ecs::error::SdkError<
+            aws_sdk_ecs::operation::register_task_definition::RegisterTaskDefinitionError,
+        >,
+    },
+
+    #[snafu(display("Task definition was missing from the response"))]
+    TaskDefinitionMissing,
+
+    #[snafu(display("Unable to describe task definition: {}", source))]
+    TaskDefinitionDescribe {
+        source: aws_sdk_ecs::error::SdkError<
+            aws_sdk_ecs::operation::describe_task_definition::DescribeTaskDefinitionError,
+        >,
+    },
+
+    #[snafu(display("Unable to fetch log events: {}", source))]
+    LogEvents {
+        source: aws_sdk_cloudwatchlogs::error::SdkError<
+            aws_sdk_cloudwatchlogs::operation::get_log_events::GetLogEventsError,
+        >,
+    },
+
+    #[snafu(display("Unable to fetch metric data: {}", source))]
+    MetricData {
+        source: aws_sdk_cloudwatch::error::SdkError<
+            aws_sdk_cloudwatch::operation::get_metric_data::GetMetricDataError,
+        >,
+    },
+
+    #[snafu(display("Unable to execute command: {}", source))]
+    ExecCommand {
+        source: aws_sdk_ecs::error::SdkError<
+            aws_sdk_ecs::operation::execute_command::ExecuteCommandError,
+        >,
+    },
+
+    #[snafu(display("execute_command did not return a session"))]
+    ExecSessionMissing,
+
+    #[snafu(display("Unable to read exec session output: {}", source))]
+    ExecSession { source: std::io::Error },
+}
@@ -0,0 +1,8 @@
+/*!
+
+Shared constants and error types for the testsys ECS agents.
+
+!*/
+
+pub mod constants;
+pub mod error;
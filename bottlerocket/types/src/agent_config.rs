@@ -0,0 +1,68 @@
+/*!
+
+Configuration types shared between the testsys controller and the agents it launches.
+
+!*/
+
+use serde::{Deserialize, Serialize};
+
+/// The name of the secret containing AWS credentials, as stored in a test's `Spec::secrets` map.
+pub const AWS_CREDENTIALS_SECRET_NAME: &str = "awsCredentials";
+
+/// Configuration for the ECS test agent, which runs a task on an ECS cluster and reports whether
+/// it completed successfully.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcsTestConfig {
+    /// The region the ECS cluster is running in.
+    pub region: Option<String>,
+    /// The role to assume before interacting with the cluster.
+    pub assume_role: Option<String>,
+    /// The name of the ECS cluster to run the test in.
+    pub cluster_name: String,
+    /// The number of tasks to run.
+    pub task_count: i32,
+    /// An existing task definition (`family:revision`) to run instead of the testsys-provided
+    /// smoke-test definition.
+    pub task_definition_name_and_revision: Option<String>,
+    /// The ECS launch type to run the task with (`"EC2"` or `"FARGATE"`). Defaults to `EC2`.
+    pub launch_type: Option<String>,
+    /// The `awsvpc` network configuration to run the task with. Required when `launch_type` is
+    /// `FARGATE`.
+    pub network_configuration: Option<EcsNetworkConfiguration>,
+    /// If set, the tail of each container's `awslogs` stream is fetched after the task stops and
+    /// attached to the test results.
+    pub log_configuration: Option<EcsLogConfiguration>,
+    /// How long to wait for the task to finish running before marking the test failed. Defaults
+    /// to `30` seconds.
+    pub timeout_seconds: Option<u64>,
+    /// If `true`, query `ECS/ContainerInsights` for each task's CPU/memory utilization after it
+    /// stops and attach the result to the test results. Defaults to `false`.
+    pub collect_metrics: Option<bool>,
+    /// Shell commands to run inside each task's essential container via ECS Exec once it starts
+    /// running. The test fails if any command exits non-zero.
+    pub exec_commands: Option<Vec<String>>,
+}
+
+/// The `awsvpc` network configuration for a task, mirroring `aws_sdk_ecs::types::AwsVpcConfiguration`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcsNetworkConfiguration {
+    /// The subnets to launch the task's elastic network interface in.
+    pub subnets: Vec<String>,
+    /// The security groups to associate with the task's elastic network interface.
+    pub security_groups: Vec<String>,
+    /// Whether to assign a public IP address to the task's elastic network interface.
+    pub assign_public_ip: Option<bool>,
+}
+
+/// Configuration for fetching a task's container logs out of CloudWatch after it stops.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EcsLogConfiguration {
+    /// The CloudWatch log group the smoke-test container's `awslogs` driver is configured to
+    /// write to.
+    pub log_group: String,
+    /// The `awslogs-stream-prefix` the smoke-test container's `awslogs` driver is configured
+    /// with. Defaults to `ecs`.
+    pub stream_prefix: Option<String>,
+    /// The number of most recent log lines to fetch per container. Defaults to `50`.
+    pub tail_lines: Option<u32>,
+}
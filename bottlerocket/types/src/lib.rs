@@ -0,0 +1,7 @@
+/*!
+
+Shared configuration types for testsys agents.
+
+!*/
+
+pub mod agent_config;